@@ -6,6 +6,7 @@
 // Copyright (c) 2019, Olof Kraigher olof.kraigher@gmail.com
 
 use super::*;
+use std::collections::HashMap;
 
 #[must_use]
 pub enum SearchResult<T> {
@@ -72,12 +73,29 @@ pub trait Searcher<T> {
     fn search_entity(&mut self, _ent: &EntityUnit) -> SearchState<T> {
         NotFinished
     }
+    fn search_architecture(&mut self, _arch: &ArchitectureUnit) -> SearchState<T> {
+        NotFinished
+    }
+    fn search_package(&mut self, _pkg: &PackageUnit) -> SearchState<T> {
+        NotFinished
+    }
+    fn search_package_body(&mut self, _pkg: &PackageBodyUnit) -> SearchState<T> {
+        NotFinished
+    }
+    // A position that resolves to a declaration; search_designator_ref and
+    // search_ident_ref both route through this
+    fn search_pos_with_ref<U>(&mut self, _pos: &SrcPos, _reference: &WithRef<U>) -> SearchState<T> {
+        NotFinished
+    }
     fn search_designator_ref(
         &mut self,
-        _pos: &SrcPos,
-        _designator: &WithRef<Designator>,
+        pos: &SrcPos,
+        designator: &WithRef<Designator>,
     ) -> SearchState<T> {
-        NotFinished
+        self.search_pos_with_ref(pos, designator)
+    }
+    fn search_ident_ref(&mut self, ident: &WithRef<Ident>) -> SearchState<T> {
+        self.search_pos_with_ref(ident.item.pos(), ident)
     }
     fn search_with_pos(&mut self, _pos: &SrcPos) -> SearchState<T> {
         NotFinished
@@ -125,7 +143,44 @@ impl<T> Search<T> for LabeledSequentialStatement {
     fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
         searcher
             .search_labeled_sequential_statement(self)
-            .or_else(|| NotFound)
+            .or_else(|| match self.statement {
+                SequentialStatement::VariableAssignment(ref assign) => {
+                    return_if!(assign.target.search(searcher));
+                    assign.value.search(searcher)
+                }
+                SequentialStatement::SignalAssignment(ref assign) => {
+                    return_if!(assign.target.search(searcher));
+                    assign.value.search(searcher)
+                }
+                SequentialStatement::ProcedureCall(ref call) => call.item.search(searcher),
+                SequentialStatement::If(ref stmt) => {
+                    for conditional in stmt.conds.conditionals.iter() {
+                        return_if!(conditional.condition.search(searcher));
+                        return_if!(conditional.item.search(searcher));
+                    }
+                    stmt.conds.else_item.search(searcher)
+                }
+                SequentialStatement::Case(ref stmt) => {
+                    return_if!(stmt.expression.search(searcher));
+                    for alternative in stmt.alternatives.iter() {
+                        return_if!(alternative.item.search(searcher));
+                    }
+                    NotFound
+                }
+                SequentialStatement::Loop(ref stmt) => stmt.statements.search(searcher),
+                SequentialStatement::Exit(ref stmt) => {
+                    return_if!(stmt.loop_label.search(searcher));
+                    stmt.condition.search(searcher)
+                }
+                SequentialStatement::Next(ref stmt) => {
+                    return_if!(stmt.loop_label.search(searcher));
+                    stmt.condition.search(searcher)
+                }
+                // Wait, assert, report, return and null statements are not
+                // descended into, so names in their expressions are not found.
+                // @TODO not searched
+                _ => NotFound,
+            })
     }
 }
 
@@ -161,6 +216,18 @@ impl<T> Search<T> for LabeledConcurrentStatement {
                     }
                     NotFound
                 }
+                ConcurrentStatement::Instance(ref instance) => {
+                    // The instantiated unit name (component/entity/configuration)
+                    // is not yet descended into.
+                    // @TODO not searched
+                    return_if!(instance.generic_map.search(searcher));
+                    instance.port_map.search(searcher)
+                }
+                ConcurrentStatement::Assignment(ref assign) => {
+                    return_if!(assign.target.search(searcher));
+                    assign.value.search(searcher)
+                }
+                ConcurrentStatement::ProcedureCall(ref call) => call.call.search(searcher),
                 // @TODO not searched
                 _ => NotFound,
             })
@@ -177,6 +244,14 @@ impl<T> Search<T> for WithPos<WithRef<Designator>> {
     }
 }
 
+impl<T> Search<T> for WithRef<Ident> {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        searcher
+            .search_with_pos(self.item.pos())
+            .or_else(|| searcher.search_ident_ref(self).or_not_found())
+    }
+}
+
 impl<T> Search<T> for WithPos<SelectedName> {
     fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
         match self.item {
@@ -192,6 +267,93 @@ impl<T> Search<T> for WithPos<SelectedName> {
     }
 }
 
+impl<T> Search<T> for WithPos<Name> {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        searcher.search_with_pos(&self.pos).or_else(|| match self.item {
+            Name::Designator(ref designator) => searcher
+                .search_designator_ref(&self.pos, designator)
+                .or_not_found(),
+            Name::Selected(ref prefix, ref suffix) => {
+                return_if!(prefix.search(searcher));
+                searcher
+                    .search_designator_ref(&suffix.pos, &suffix.item)
+                    .or_not_found()
+            }
+            Name::SelectedAll(ref prefix) => prefix.search(searcher),
+            Name::CallOrIndexed(ref call) => call.search(searcher),
+            // Slices, attributes and external names are not yet descended
+            // into.
+            // @TODO not searched
+            _ => NotFound,
+        })
+    }
+}
+
+impl<T> Search<T> for CallOrIndexed {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        return_if!(self.name.search(searcher));
+        self.parameters.search(searcher)
+    }
+}
+
+impl<T> Search<T> for AssociationElement {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        return_if!(self.formal.search(searcher));
+        self.actual.search(searcher)
+    }
+}
+
+impl<T> Search<T> for WithPos<ActualPart> {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        searcher.search_with_pos(&self.pos).or_else(|| match self.item {
+            ActualPart::Expression(ref expr) => expr.search(searcher),
+            ActualPart::Open => NotFound,
+        })
+    }
+}
+
+impl<T> Search<T> for Expression {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        match self {
+            Expression::Binary(_, ref left, ref right) => {
+                return_if!(left.search(searcher));
+                right.search(searcher)
+            }
+            Expression::Unary(_, ref expr) => expr.search(searcher),
+            Expression::Name(ref name) => name.search(searcher),
+            // Aggregates, qualified expressions, allocators and literals are
+            // not descended into, so names and type marks inside them are not
+            // found.
+            // @TODO not searched
+            _ => NotFound,
+        }
+    }
+}
+
+impl<T> Search<T> for WithPos<Expression> {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        searcher
+            .search_with_pos(&self.pos)
+            .or_else(|| self.item.search(searcher))
+    }
+}
+
+impl<T> Search<T> for Waveform {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        match self {
+            Waveform::Elements(ref elements) => elements.search(searcher),
+            Waveform::Unaffected => NotFound,
+        }
+    }
+}
+
+impl<T> Search<T> for WaveformElement {
+    fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
+        return_if!(self.value.search(searcher));
+        self.after.search(searcher)
+    }
+}
+
 impl<T> Search<T> for SubtypeIndication {
     fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
         searcher.search_subtype_indication(&self).or_else(|| {
@@ -323,8 +485,10 @@ impl<T> Search<T> for EntityUnit {
 impl<T> Search<T> for ArchitectureUnit {
     fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
         searcher.search_source(self.source()).or_else(|| {
-            return_if!(self.unit.decl.search(searcher));
-            self.unit.statements.search(searcher)
+            searcher.search_architecture(self).or_else(|| {
+                return_if!(self.unit.decl.search(searcher));
+                self.unit.statements.search(searcher)
+            })
         })
     }
 }
@@ -332,17 +496,21 @@ impl<T> Search<T> for ArchitectureUnit {
 impl<T> Search<T> for PackageUnit {
     fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
         searcher.search_source(self.source()).or_else(|| {
-            return_if!(self.unit.generic_clause.search(searcher));
-            self.unit.decl.search(searcher)
+            searcher.search_package(self).or_else(|| {
+                return_if!(self.unit.generic_clause.search(searcher));
+                self.unit.decl.search(searcher)
+            })
         })
     }
 }
 
 impl<T> Search<T> for PackageBodyUnit {
     fn search(&self, searcher: &mut impl Searcher<T>) -> SearchResult<T> {
-        searcher
-            .search_source(self.source())
-            .or_else(|| self.unit.decl.search(searcher))
+        searcher.search_source(self.source()).or_else(|| {
+            searcher
+                .search_package_body(self)
+                .or_else(|| self.unit.decl.search(searcher))
+        })
     }
 }
 
@@ -403,15 +571,11 @@ impl Searcher<SrcPos> for ItemAtCursor {
         }
     }
 
-    fn search_designator_ref(
-        &mut self,
-        pos: &SrcPos,
-        designator: &WithRef<Designator>,
-    ) -> SearchState<SrcPos> {
+    fn search_pos_with_ref<U>(&mut self, pos: &SrcPos, reference: &WithRef<U>) -> SearchState<SrcPos> {
         if !self.is_inside(pos) {
             Finished(NotFound)
-        } else if let Some(ref reference) = designator.reference {
-            Finished(Found(reference.clone()))
+        } else if let Some(ref decl_pos) = reference.reference {
+            Finished(Found(decl_pos.clone()))
         } else {
             Finished(NotFound)
         }
@@ -434,20 +598,46 @@ impl Searcher<SrcPos> for ItemAtCursor {
     }
 }
 
+// Bounds how much of a project FindAllReferences needs to traverse
+pub enum SearchScope {
+    WholeLibrary,
+    Sources(Vec<Source>),
+    SingleFile(Source),
+}
+
+impl SearchScope {
+    fn contains(&self, source: &Source) -> bool {
+        match self {
+            SearchScope::WholeLibrary => true,
+            SearchScope::Sources(sources) => sources.contains(source),
+            SearchScope::SingleFile(single) => single == source,
+        }
+    }
+}
+
 // Search for all reference to declaration/defintion
 pub struct FindAllReferences {
     decl_pos: SrcPos,
+    name: String,
+    scope: SearchScope,
     references: Vec<SrcPos>,
 }
 
 impl FindAllReferences {
-    pub fn new(decl_pos: &SrcPos) -> FindAllReferences {
+    pub fn new(decl_pos: &SrcPos, name: &str) -> FindAllReferences {
         FindAllReferences {
             decl_pos: decl_pos.clone(),
+            name: name.to_ascii_lowercase(),
+            scope: SearchScope::WholeLibrary,
             references: Vec::new(),
         }
     }
 
+    pub fn in_scope(mut self, scope: SearchScope) -> FindAllReferences {
+        self.scope = scope;
+        self
+    }
+
     pub fn search(mut self, searchable: &impl Search<()>) -> Vec<SrcPos> {
         let _unnused = searchable.search(&mut self);
         self.references
@@ -461,21 +651,1169 @@ impl FindAllReferences {
 }
 
 impl Searcher<()> for FindAllReferences {
+    fn search_source(&mut self, source: &Source) -> SearchState<()> {
+        if !self.scope.contains(source) {
+            return Finished(NotFound);
+        }
+
+        // Optimization only: skip the full traversal below when the source text doesn't even
+        // contain the identifier, on word boundaries so `clk` doesn't match `clkdiv`
+        if contains_identifier(&source.contents().to_string(), &self.name) {
+            NotFinished
+        } else {
+            Finished(NotFound)
+        }
+    }
+
     fn search_entity(&mut self, ent: &EntityUnit) -> SearchState<()> {
         self.search_decl_pos(ent.ident().pos());
         NotFinished
     }
 
-    fn search_designator_ref(
-        &mut self,
-        pos: &SrcPos,
-        designator: &WithRef<Designator>,
-    ) -> SearchState<()> {
-        if let Some(ref reference) = designator.reference {
-            if reference == &self.decl_pos {
+    fn search_architecture(&mut self, arch: &ArchitectureUnit) -> SearchState<()> {
+        self.search_decl_pos(arch.ident().pos());
+        NotFinished
+    }
+
+    fn search_package(&mut self, pkg: &PackageUnit) -> SearchState<()> {
+        self.search_decl_pos(pkg.ident().pos());
+        NotFinished
+    }
+
+    fn search_package_body(&mut self, pkg: &PackageBodyUnit) -> SearchState<()> {
+        self.search_decl_pos(pkg.ident().pos());
+        NotFinished
+    }
+
+    // Declarations are never visited by Declaration::search itself (it only
+    // descends into subtype indications, specifications, ...), so their own
+    // identifier has to be picked up here or a rename never touches the
+    // declaration site, only its usages
+    fn search_declaration(&mut self, decl: &Declaration) -> SearchState<()> {
+        match decl {
+            Declaration::Object(object) => self.search_decl_pos(object.ident.pos()),
+            Declaration::Type(typ) => self.search_decl_pos(typ.ident.pos()),
+            Declaration::SubprogramBody(body) => {
+                self.search_decl_pos(subprogram_pos(&body.specification))
+            }
+            Declaration::SubprogramDeclaration(decl) => {
+                self.search_decl_pos(subprogram_pos(decl))
+            }
+            _ => {}
+        }
+        NotFinished
+    }
+
+    fn search_pos_with_ref<U>(&mut self, pos: &SrcPos, reference: &WithRef<U>) -> SearchState<()> {
+        if let Some(ref decl_pos) = reference.reference {
+            if decl_pos == &self.decl_pos {
                 self.references.push(pos.clone());
             }
         };
         NotFinished
     }
+}
+
+// Rename of the declaration at decl_pos to new_name, built on FindAllReferences
+pub struct Rename {
+    decl_pos: SrcPos,
+    name: String,
+    new_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    // new_name is not a legal VHDL identifier or extended identifier, or is a reserved word
+    InvalidIdentifier(String),
+    // new_name collides with another declaration visible in the same region
+    WouldShadow(SrcPos),
+}
+
+impl Rename {
+    // visible is the set of (name, pos) of other declarations visible in the same region as
+    // decl_pos, gathered by the caller from semantic analysis since this module only sees syntax
+    pub fn new(
+        decl_pos: &SrcPos,
+        name: &str,
+        new_name: &str,
+        visible: &[(String, SrcPos)],
+    ) -> Result<Rename, RenameError> {
+        if !is_legal_identifier(new_name) {
+            return Err(RenameError::InvalidIdentifier(new_name.to_owned()));
+        }
+
+        if let Some((_, pos)) = visible
+            .iter()
+            .find(|(other_name, pos)| identifiers_equal(other_name, new_name) && pos != decl_pos)
+        {
+            return Err(RenameError::WouldShadow(pos.clone()));
+        }
+
+        Ok(Rename {
+            decl_pos: decl_pos.clone(),
+            name: name.to_owned(),
+            new_name: new_name.to_owned(),
+        })
+    }
+
+    pub fn edits(
+        &self,
+        searchable: &impl Search<()>,
+        scope: SearchScope,
+    ) -> HashMap<Source, Vec<(SrcPos, String)>> {
+        let mut edits: HashMap<Source, Vec<(SrcPos, String)>> = HashMap::new();
+        let references = FindAllReferences::new(&self.decl_pos, &self.name)
+            .in_scope(scope)
+            .search(searchable);
+        for pos in references {
+            edits
+                .entry(pos.source.clone())
+                .or_insert_with(Vec::new)
+                .push((pos, self.new_name.clone()));
+        }
+        edits
+    }
+}
+
+// VHDL-2008 reserved words (LRM 15.10), plus `view` from VHDL-2019; a basic identifier cannot
+// be any of these
+const RESERVED_WORDS: &[&str] = &[
+    "abs", "access", "after", "alias", "all", "and", "architecture", "array", "assert",
+    "assume", "assume_guarantee", "attribute", "begin", "block", "body", "buffer", "bus", "case", "component",
+    "configuration", "constant", "context", "cover", "default", "disconnect", "downto", "else",
+    "elsif", "end", "entity", "exit", "fairness", "file", "for", "force", "function", "generate",
+    "generic", "group", "guarded", "if", "impure", "in", "inertial", "inout", "is", "label",
+    "library", "linkage", "literal", "loop", "map", "mod", "nand", "new", "next", "nor", "not",
+    "null", "of", "on", "open", "or", "others", "out", "package", "parameter", "port",
+    "postponed", "procedure", "process", "property", "protected", "pure", "range", "record",
+    "register", "reject", "release", "rem", "report", "restrict", "restrict_guarantee", "return", "rol", "ror",
+    "select", "sequence", "severity", "shared", "signal", "sla", "sll", "sra", "srl", "strong",
+    "subtype", "then", "to", "transport", "type", "unaffected", "units", "until", "use",
+    "variable", "view", "vmode", "vprop", "vunit", "wait", "when", "while", "with", "xnor", "xor",
+];
+
+fn is_legal_identifier(name: &str) -> bool {
+    if name.starts_with('\\') {
+        return is_legal_extended_identifier(name);
+    }
+
+    if !is_basic_identifier_syntax(name) {
+        return false;
+    }
+
+    !RESERVED_WORDS.iter().any(|word| word.eq_ignore_ascii_case(name))
+}
+
+fn is_basic_identifier_syntax(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(ch) if ch.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+
+    let mut prev_underscore = false;
+    for ch in chars {
+        match ch {
+            '_' => {
+                if prev_underscore {
+                    return false;
+                }
+                prev_underscore = true;
+            }
+            ch if ch.is_ascii_alphanumeric() => prev_underscore = false,
+            _ => return false,
+        }
+    }
+
+    !name.ends_with('_')
+}
+
+fn is_legal_extended_identifier(name: &str) -> bool {
+    match name.strip_prefix('\\').and_then(|rest| rest.strip_suffix('\\')) {
+        // A backslash inside an extended identifier must be doubled
+        Some(inner) => {
+            !inner.is_empty() && !inner.contains('\n') && !inner.replace("\\\\", "").contains('\\')
+        }
+        None => false,
+    }
+}
+
+// Basic identifiers are case-insensitive, extended identifiers are case-sensitive and never
+// equal to a basic identifier even if spelled the same (LRM 15.4.3)
+fn identifiers_equal(a: &str, b: &str) -> bool {
+    let a_extended = a.starts_with('\\');
+    let b_extended = b.starts_with('\\');
+    if a_extended || b_extended {
+        a_extended == b_extended && a == b
+    } else {
+        a.eq_ignore_ascii_case(b)
+    }
+}
+
+// Whether text contains name as a whole identifier token, case insensitively
+fn contains_identifier(text: &str, name: &str) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+
+    let text = text.to_ascii_lowercase();
+    let name = name.to_ascii_lowercase();
+    let name = name.as_str();
+    let bytes = text.as_bytes();
+    let is_word_byte = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+
+    let mut start = 0;
+    while let Some(offset) = text[start..].find(name) {
+        let idx = start + offset;
+        let before_ok = idx == 0 || !is_word_byte(bytes[idx - 1]);
+        let after = idx + name.len();
+        let after_ok = after >= bytes.len() || !is_word_byte(bytes[after]);
+        if before_ok && after_ok {
+            return true;
+        }
+        start = idx + 1;
+    }
+
+    false
+}
+
+// Slice the text of `contents` (the full text of pos.source) spanned by pos
+fn slice_from(contents: &str, pos: &SrcPos) -> String {
+    let mut result = String::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        let line_idx = line_idx as u64;
+        if line_idx < pos.range.start.line || line_idx > pos.range.end.line {
+            continue;
+        }
+
+        let start_char = if line_idx == pos.range.start.line {
+            pos.range.start.character as usize
+        } else {
+            0
+        };
+        let end_char = if line_idx == pos.range.end.line {
+            pos.range.end.character as usize
+        } else {
+            line.chars().count()
+        };
+
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(
+            &line
+                .chars()
+                .skip(start_char)
+                .take(end_char.saturating_sub(start_char))
+                .collect::<String>(),
+        );
+    }
+    result
+}
+
+// Which kind of AST node a pattern placeholder is allowed to bind to. A
+// Name placeholder must not match an arbitrary expression, and a Statement
+// placeholder (bound by AssignmentPattern::Statement) matches a whole
+// labeled statement rather than anything inside one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderKind {
+    Name,
+    Expression,
+    Statement,
+}
+
+// A pattern target or value, parsed from pattern source text into the same
+// shape Name::Selected/Designator already distinguish
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternName {
+    Placeholder(String),
+    Simple(String),
+    Selected(Box<PatternName>, String),
+}
+
+// A pattern value, parsed into the same shape Expression::Binary/Unary/Name
+// already distinguish. Aggregates, qualified expressions and literals are
+// not parsed by this tokenizer and so can't appear in a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternExpr {
+    Placeholder(String, PlaceholderKind),
+    NameRef(PatternName),
+    Binary(String, Box<PatternExpr>, Box<PatternExpr>),
+    Unary(String, Box<PatternExpr>),
+}
+
+// A search-and-replace pattern built on the `Search`/`Searcher` traits: a
+// parsed pattern is structurally unified against candidate AST subtrees
+// (node kinds must match, literal sub-nodes must be equal, and a placeholder
+// binds to whatever subtree occupies its position), capturing each
+// placeholder's SrcPos span rather than its text.
+pub enum AssignmentPattern {
+    // `$s;` matches any whole labeled statement
+    Statement(String, PlaceholderKind),
+    // `<target> <= <value>;`, matching a simple, single-waveform-element
+    // signal assignment (no `after` clauses, no conditional waveforms)
+    Assignment {
+        target: PatternName,
+        value: PatternExpr,
+    },
+}
+
+impl AssignmentPattern {
+    pub fn parse(pattern: &str) -> Option<AssignmentPattern> {
+        let pattern = pattern.trim().strip_suffix(';').unwrap_or(pattern).trim();
+
+        if let Some(name) = pattern.strip_prefix('$') {
+            if !name.is_empty() && !name.contains(char::is_whitespace) {
+                return Some(AssignmentPattern::Statement(
+                    name.to_owned(),
+                    PlaceholderKind::Statement,
+                ));
+            }
+        }
+
+        let (target, value) = pattern.split_once("<=")?;
+        Some(AssignmentPattern::Assignment {
+            target: parse_name(target.trim())?,
+            value: parse_expr(value.trim())?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Dot,
+    LParen,
+    RParen,
+    Op(String),
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    const TWO_CHAR_OPS: &[&str] = &["/=", ">=", "<="];
+    let mut tokens = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap();
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+        match c {
+            '.' => {
+                tokens.push(Token::Dot);
+                i += 1;
+                continue;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+                continue;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        if c == '$' || c.is_ascii_alphabetic() || c == '\\' {
+            let start = i;
+            if c == '\\' {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'\\' {
+                    i += 1;
+                }
+                i = (i + 1).min(bytes.len());
+            } else {
+                if c == '$' {
+                    i += 1;
+                }
+                while i < bytes.len()
+                    && (text[i..].chars().next().unwrap().is_ascii_alphanumeric()
+                        || bytes[i] == b'_')
+                {
+                    i += 1;
+                }
+            }
+            tokens.push(Token::Ident(text[start..i].to_owned()));
+            continue;
+        }
+        if let Some(op) = TWO_CHAR_OPS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Op((*op).to_owned()));
+            i += 2;
+            continue;
+        }
+        if "+-*/&=<>".contains(c) {
+            tokens.push(Token::Op(c.to_string()));
+            i += 1;
+            continue;
+        }
+        return None;
+    }
+    Some(tokens)
+}
+
+const LOGICAL: &[&str] = &["and", "or", "xor", "nand", "nor", "xnor"];
+const RELATIONAL: &[&str] = &["=", "/=", "<", "<=", ">", ">="];
+const SHIFT: &[&str] = &["sll", "srl", "sla", "sra", "rol", "ror"];
+const ADDING: &[&str] = &["+", "-", "&"];
+const MULTIPLYING: &[&str] = &["*", "/", "mod", "rem"];
+const LEVELS: &[&[&str]] = &[LOGICAL, RELATIONAL, SHIFT, ADDING, MULTIPLYING];
+
+struct PatternParser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+}
+
+impl<'t> PatternParser<'t> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn bump(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn peek_operator(&self, ops: &[&str]) -> Option<String> {
+        match self.peek() {
+            Some(Token::Op(text)) if ops.contains(&text.as_str()) => Some(text.clone()),
+            Some(Token::Ident(text)) if ops.iter().any(|op| op.eq_ignore_ascii_case(text)) => {
+                Some(text.to_ascii_lowercase())
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_level(&mut self, level: usize) -> Option<PatternExpr> {
+        if level >= LEVELS.len() {
+            return self.parse_unary();
+        }
+        let mut left = self.parse_level(level + 1)?;
+        while let Some(op) = self.peek_operator(LEVELS[level]) {
+            self.bump();
+            let right = self.parse_level(level + 1)?;
+            left = PatternExpr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    fn parse_unary(&mut self) -> Option<PatternExpr> {
+        if let Some(op) = self.peek_operator(&["+", "-", "not"]) {
+            self.bump();
+            return Some(PatternExpr::Unary(op, Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Option<PatternExpr> {
+        match self.bump()?.clone() {
+            Token::LParen => {
+                let inner = self.parse_level(0)?;
+                match self.bump() {
+                    Some(Token::RParen) => Some(inner),
+                    _ => None,
+                }
+            }
+            Token::Ident(text) => {
+                if let Some(name) = text.strip_prefix('$') {
+                    if name.is_empty() {
+                        return None;
+                    }
+                    return Some(PatternExpr::Placeholder(
+                        name.to_owned(),
+                        PlaceholderKind::Expression,
+                    ));
+                }
+                Some(PatternExpr::NameRef(
+                    self.parse_name_rest(PatternName::Simple(text))?,
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_name_rest(&mut self, mut name: PatternName) -> Option<PatternName> {
+        while matches!(self.peek(), Some(Token::Dot)) {
+            self.bump();
+            match self.bump()?.clone() {
+                Token::Ident(text) if !text.starts_with('$') => {
+                    name = PatternName::Selected(Box::new(name), text);
+                }
+                _ => return None,
+            }
+        }
+        Some(name)
+    }
+}
+
+fn parse_name(text: &str) -> Option<PatternName> {
+    let tokens = tokenize(text)?;
+    let mut parser = PatternParser {
+        tokens: &tokens,
+        pos: 0,
+    };
+    let name = match parser.bump()?.clone() {
+        Token::Ident(text) => match text.strip_prefix('$') {
+            Some(placeholder) if !placeholder.is_empty() => {
+                PatternName::Placeholder(placeholder.to_owned())
+            }
+            Some(_) => return None,
+            None => parser.parse_name_rest(PatternName::Simple(text))?,
+        },
+        _ => return None,
+    };
+    if parser.pos == tokens.len() {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn parse_expr(text: &str) -> Option<PatternExpr> {
+    let tokens = tokenize(text)?;
+    let mut parser = PatternParser {
+        tokens: &tokens,
+        pos: 0,
+    };
+    let expr = parser.parse_level(0)?;
+    if parser.pos == tokens.len() {
+        Some(expr)
+    } else {
+        None
+    }
+}
+
+// Slice the text spanned by pos out of the already-cached contents of its source
+fn operator_text(source: &str, pos: &SrcPos) -> String {
+    slice_from(source, pos).trim().to_owned()
+}
+
+// Bind name to pos, or require it be structurally (here: textually) equal to
+// its previous binding if the same placeholder appears twice in the pattern
+fn bind<'p>(
+    name: &'p str,
+    pos: &SrcPos,
+    source: &str,
+    bindings: &mut HashMap<&'p str, SrcPos>,
+) -> bool {
+    match bindings.get(name) {
+        Some(bound) => slice_from(source, bound).trim() == slice_from(source, pos).trim(),
+        None => {
+            bindings.insert(name, pos.clone());
+            true
+        }
+    }
+}
+
+fn match_name<'p>(
+    pattern: &'p PatternName,
+    target: &WithPos<Name>,
+    source: &str,
+    bindings: &mut HashMap<&'p str, SrcPos>,
+) -> bool {
+    match pattern {
+        PatternName::Placeholder(name) => bind(name, &target.pos, source, bindings),
+        PatternName::Simple(text) => match &target.item {
+            Name::Designator(designator) => identifiers_equal(&designator.item.to_string(), text),
+            _ => false,
+        },
+        PatternName::Selected(prefix, suffix) => match &target.item {
+            Name::Selected(target_prefix, target_suffix) => {
+                match_name(prefix, target_prefix, source, bindings)
+                    && identifiers_equal(&target_suffix.item.item.to_string(), suffix)
+            }
+            _ => false,
+        },
+    }
+}
+
+fn match_expr<'p>(
+    pattern: &'p PatternExpr,
+    target: &WithPos<Expression>,
+    source: &str,
+    bindings: &mut HashMap<&'p str, SrcPos>,
+) -> bool {
+    match pattern {
+        PatternExpr::Placeholder(name, PlaceholderKind::Name) => {
+            matches!(target.item, Expression::Name(_)) && bind(name, &target.pos, source, bindings)
+        }
+        PatternExpr::Placeholder(name, _) => bind(name, &target.pos, source, bindings),
+        PatternExpr::NameRef(pattern_name) => match &target.item {
+            Expression::Name(name) => match_name(pattern_name, name, source, bindings),
+            _ => false,
+        },
+        PatternExpr::Binary(op, left, right) => match &target.item {
+            Expression::Binary(target_op, target_left, target_right) => {
+                operator_text(source, &target_op.pos).eq_ignore_ascii_case(op)
+                    && match_expr(left, target_left, source, bindings)
+                    && match_expr(right, target_right, source, bindings)
+            }
+            _ => false,
+        },
+        PatternExpr::Unary(op, operand) => match &target.item {
+            Expression::Unary(target_op, target_operand) => {
+                operator_text(source, &target_op.pos).eq_ignore_ascii_case(op)
+                    && match_expr(operand, target_operand, source, bindings)
+            }
+            _ => false,
+        },
+    }
+}
+
+// Substitute `$name` placeholders in template with their bound text. Names
+// are replaced longest-first, and only when followed by a non-identifier
+// character (or end of string), so `$x1` can't be corrupted by a `$x` match.
+fn substitute_bindings(template: &str, bindings: &HashMap<&str, String>) -> String {
+    let mut names: Vec<&str> = bindings.keys().copied().collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+
+    let mut result = String::new();
+    let mut rest = template;
+    'outer: while !rest.is_empty() {
+        for name in &names {
+            let marker = format!("${}", name);
+            if let Some(after) = rest.strip_prefix(marker.as_str()) {
+                let is_boundary = after
+                    .chars()
+                    .next()
+                    .map_or(true, |c| !(c.is_ascii_alphanumeric() || c == '_'));
+                if is_boundary {
+                    result.push_str(&bindings[name]);
+                    rest = after;
+                    continue 'outer;
+                }
+            }
+        }
+        let mut chars = rest.chars();
+        result.push(chars.next().unwrap());
+        rest = chars.as_str();
+    }
+    result
+}
+
+// Walks a target AST looking for statements matching an AssignmentPattern,
+// producing one text edit per match with placeholders substituted into
+// `replacement`
+pub struct StructuralReplace<'a> {
+    pattern: &'a AssignmentPattern,
+    replacement: &'a str,
+    source_text: HashMap<Source, String>,
+    edits: Vec<(SrcPos, String)>,
+}
+
+impl<'a> StructuralReplace<'a> {
+    pub fn new(pattern: &'a AssignmentPattern, replacement: &'a str) -> StructuralReplace<'a> {
+        StructuralReplace {
+            pattern,
+            replacement,
+            source_text: HashMap::new(),
+            edits: Vec::new(),
+        }
+    }
+
+    pub fn search(mut self, searchable: &impl Search<()>) -> Vec<(SrcPos, String)> {
+        let _unused = searchable.search(&mut self);
+        self.edits
+    }
+
+    fn source_text(&mut self, pos: &SrcPos) -> String {
+        self.source_text
+            .entry(pos.source.clone())
+            .or_insert_with(|| pos.source.contents().to_string())
+            .clone()
+    }
+
+    fn try_match_whole_statement(&mut self, name: &str, stmt_pos: &SrcPos) {
+        let mut edits = HashMap::new();
+        edits.insert(name, stmt_pos.clone());
+        self.push_edit(stmt_pos, &edits);
+    }
+
+    fn try_match_assignment(
+        &mut self,
+        target: &PatternName,
+        value: &PatternExpr,
+        assign_target: &WithPos<Name>,
+        assign_value: &Waveform,
+        stmt_pos: &SrcPos,
+    ) {
+        let element = match assign_value {
+            Waveform::Elements(elements) if elements.len() == 1 && elements[0].after.is_none() => {
+                &elements[0]
+            }
+            _ => return,
+        };
+
+        let source = self.source_text(stmt_pos);
+        let mut bindings = HashMap::new();
+        if match_name(target, assign_target, &source, &mut bindings)
+            && match_expr(value, &element.value, &source, &mut bindings)
+        {
+            self.push_edit(stmt_pos, &bindings);
+        }
+    }
+
+    fn push_edit(&mut self, stmt_pos: &SrcPos, bindings: &HashMap<&str, SrcPos>) {
+        let source = self.source_text(stmt_pos);
+        let rendered: HashMap<&str, String> = bindings
+            .iter()
+            .map(|(name, pos)| (*name, slice_from(&source, pos)))
+            .collect();
+        self.edits.push((
+            stmt_pos.clone(),
+            substitute_bindings(self.replacement, &rendered),
+        ));
+    }
+}
+
+impl<'a> Searcher<()> for StructuralReplace<'a> {
+    fn search_labeled_concurrent_statement(
+        &mut self,
+        stmt: &LabeledConcurrentStatement,
+    ) -> SearchState<()> {
+        match self.pattern {
+            AssignmentPattern::Statement(name, _) => {
+                self.try_match_whole_statement(name, &stmt.pos)
+            }
+            AssignmentPattern::Assignment { target, value } => {
+                if let ConcurrentStatement::Assignment(ref assign) = stmt.statement {
+                    self.try_match_assignment(
+                        target,
+                        value,
+                        &assign.target,
+                        &assign.value,
+                        &stmt.pos,
+                    );
+                }
+            }
+        }
+        NotFinished
+    }
+
+    fn search_labeled_sequential_statement(
+        &mut self,
+        stmt: &LabeledSequentialStatement,
+    ) -> SearchState<()> {
+        match self.pattern {
+            AssignmentPattern::Statement(name, _) => {
+                self.try_match_whole_statement(name, &stmt.pos)
+            }
+            AssignmentPattern::Assignment { target, value } => {
+                if let SequentialStatement::SignalAssignment(ref assign) = stmt.statement {
+                    self.try_match_assignment(
+                        target,
+                        value,
+                        &assign.target,
+                        &assign.value,
+                        &stmt.pos,
+                    );
+                }
+            }
+        }
+        NotFinished
+    }
+}
+
+// One entry in a document's symbol outline: entities, architectures,
+// packages, processes (by label), subprograms, types and signals/constants,
+// nested to mirror the AST — a process's declared signals sit under the
+// process, a package's types under the package, and so on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: DocumentSymbolKind,
+    pub pos: SrcPos,
+    pub children: Vec<DocumentSymbol>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentSymbolKind {
+    Entity,
+    Architecture,
+    Package,
+    PackageBody,
+    Block,
+    Process,
+    Subprogram,
+    Type,
+    Constant,
+    Signal,
+    Variable,
+}
+
+// Builds a DocumentSymbol outline, driven by the same search_declaration and
+// search_labeled_concurrent_statement hooks as the rest of this module. A
+// stack of not-yet-closed symbols tracks declarative/statement nesting: a
+// container hook (entity, architecture, ..., process, block) pushes itself,
+// manually recurses into its children so they nest under it, then pops
+// itself into its own parent's children (or the root list) before its hook
+// returns Finished(NotFound) to suppress the default traversal it already did.
+pub struct DocumentSymbols {
+    stack: Vec<DocumentSymbol>,
+    roots: Vec<DocumentSymbol>,
+}
+
+impl DocumentSymbols {
+    // Build the outline for a single entity, architecture, package or package
+    // body unit
+    pub fn document_symbols(unit: &impl Search<()>) -> Vec<DocumentSymbol> {
+        let mut symbols = DocumentSymbols {
+            stack: Vec::new(),
+            roots: Vec::new(),
+        };
+        let _unused = unit.search(&mut symbols);
+        symbols.roots
+    }
+
+    fn enter(&mut self, symbol: DocumentSymbol) {
+        self.stack.push(symbol);
+    }
+
+    fn leave(&mut self) {
+        if let Some(symbol) = self.stack.pop() {
+            self.push_leaf(symbol);
+        }
+    }
+
+    fn push_leaf(&mut self, symbol: DocumentSymbol) {
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(symbol),
+            None => self.roots.push(symbol),
+        }
+    }
+}
+
+impl Searcher<()> for DocumentSymbols {
+    fn search_entity(&mut self, ent: &EntityUnit) -> SearchState<()> {
+        self.enter(DocumentSymbol {
+            name: ent.ident().to_string(),
+            kind: DocumentSymbolKind::Entity,
+            pos: ent.ident().pos().clone(),
+            children: Vec::new(),
+        });
+        let _unused = ent.unit.generic_clause.search(self);
+        let _unused = ent.unit.port_clause.search(self);
+        let _unused = ent.unit.decl.search(self);
+        let _unused = ent.unit.statements.search(self);
+        self.leave();
+        Finished(NotFound)
+    }
+
+    fn search_architecture(&mut self, arch: &ArchitectureUnit) -> SearchState<()> {
+        self.enter(DocumentSymbol {
+            name: arch.ident().to_string(),
+            kind: DocumentSymbolKind::Architecture,
+            pos: arch.ident().pos().clone(),
+            children: Vec::new(),
+        });
+        let _unused = arch.unit.decl.search(self);
+        let _unused = arch.unit.statements.search(self);
+        self.leave();
+        Finished(NotFound)
+    }
+
+    fn search_package(&mut self, pkg: &PackageUnit) -> SearchState<()> {
+        self.enter(DocumentSymbol {
+            name: pkg.ident().to_string(),
+            kind: DocumentSymbolKind::Package,
+            pos: pkg.ident().pos().clone(),
+            children: Vec::new(),
+        });
+        let _unused = pkg.unit.decl.search(self);
+        self.leave();
+        Finished(NotFound)
+    }
+
+    fn search_package_body(&mut self, pkg: &PackageBodyUnit) -> SearchState<()> {
+        self.enter(DocumentSymbol {
+            name: pkg.ident().to_string(),
+            kind: DocumentSymbolKind::PackageBody,
+            pos: pkg.ident().pos().clone(),
+            children: Vec::new(),
+        });
+        let _unused = pkg.unit.decl.search(self);
+        self.leave();
+        Finished(NotFound)
+    }
+
+    fn search_labeled_concurrent_statement(
+        &mut self,
+        stmt: &LabeledConcurrentStatement,
+    ) -> SearchState<()> {
+        let label = match &stmt.label {
+            Some(label) => label,
+            None => return NotFinished,
+        };
+        match stmt.statement {
+            ConcurrentStatement::Process(ref process) => {
+                self.enter(DocumentSymbol {
+                    name: label.to_string(),
+                    kind: DocumentSymbolKind::Process,
+                    pos: label.pos().clone(),
+                    children: Vec::new(),
+                });
+                let _unused = process.decl.search(self);
+                self.leave();
+                Finished(NotFound)
+            }
+            ConcurrentStatement::Block(ref block) => {
+                self.enter(DocumentSymbol {
+                    name: label.to_string(),
+                    kind: DocumentSymbolKind::Block,
+                    pos: label.pos().clone(),
+                    children: Vec::new(),
+                });
+                let _unused = block.decl.search(self);
+                let _unused = block.statements.search(self);
+                self.leave();
+                Finished(NotFound)
+            }
+            // Generate statements don't get a symbol of their own, but
+            // default traversal must continue into their bodies so a
+            // process or block nested inside one is still found.
+            _ => NotFinished,
+        }
+    }
+
+    // Entity generics and ports are InterfaceDeclarations, not Declarations,
+    // so they need their own hook to show up in the outline alongside the
+    // entity's regular declarations
+    fn search_interface_declaration(&mut self, decl: &InterfaceDeclaration) -> SearchState<()> {
+        if let InterfaceDeclaration::Object(object) = decl {
+            self.push_leaf(DocumentSymbol {
+                name: object.ident.to_string(),
+                kind: match object.class {
+                    ObjectClass::Constant => DocumentSymbolKind::Constant,
+                    ObjectClass::Variable | ObjectClass::SharedVariable => {
+                        DocumentSymbolKind::Variable
+                    }
+                    ObjectClass::Signal => DocumentSymbolKind::Signal,
+                },
+                pos: object.ident.pos().clone(),
+                children: Vec::new(),
+            });
+            Finished(NotFound)
+        } else {
+            NotFinished
+        }
+    }
+
+    fn search_declaration(&mut self, decl: &Declaration) -> SearchState<()> {
+        match decl {
+            Declaration::Object(object) => {
+                self.push_leaf(DocumentSymbol {
+                    name: object.ident.to_string(),
+                    kind: match object.class {
+                        ObjectClass::Constant => DocumentSymbolKind::Constant,
+                        ObjectClass::Variable | ObjectClass::SharedVariable => {
+                            DocumentSymbolKind::Variable
+                        }
+                        ObjectClass::Signal => DocumentSymbolKind::Signal,
+                    },
+                    pos: object.ident.pos().clone(),
+                    children: Vec::new(),
+                });
+                Finished(NotFound)
+            }
+            Declaration::Type(typ) => {
+                self.push_leaf(DocumentSymbol {
+                    name: typ.ident.to_string(),
+                    kind: DocumentSymbolKind::Type,
+                    pos: typ.ident.pos().clone(),
+                    children: protected_type_children(typ),
+                });
+                Finished(NotFound)
+            }
+            Declaration::SubprogramBody(body) => {
+                self.enter(DocumentSymbol {
+                    name: subprogram_designator(&body.specification),
+                    kind: DocumentSymbolKind::Subprogram,
+                    pos: subprogram_pos(&body.specification).clone(),
+                    children: Vec::new(),
+                });
+                let _unused = body.declarations.search(self);
+                self.leave();
+                Finished(NotFound)
+            }
+            Declaration::SubprogramDeclaration(decl) => {
+                self.push_leaf(DocumentSymbol {
+                    name: subprogram_designator(decl),
+                    kind: DocumentSymbolKind::Subprogram,
+                    pos: subprogram_pos(decl).clone(),
+                    children: Vec::new(),
+                });
+                Finished(NotFound)
+            }
+            _ => NotFinished,
+        }
+    }
+}
+
+// ProtectedTypeDeclarativeItem has no search_declaration-style hook of its
+// own, so a protected type's subprograms are still gathered directly here
+fn protected_type_children(typ: &TypeDeclaration) -> Vec<DocumentSymbol> {
+    match typ.def {
+        TypeDefinition::Protected(ref prot_decl) => prot_decl
+            .items
+            .iter()
+            .map(|item| match item {
+                ProtectedTypeDeclarativeItem::Subprogram(subprogram) => DocumentSymbol {
+                    name: subprogram_designator(subprogram),
+                    kind: DocumentSymbolKind::Subprogram,
+                    pos: subprogram_pos(subprogram).clone(),
+                    children: Vec::new(),
+                },
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn subprogram_designator(spec: &SubprogramDeclaration) -> String {
+    match spec {
+        SubprogramDeclaration::Function(decl) => decl.designator.to_string(),
+        SubprogramDeclaration::Procedure(decl) => decl.designator.to_string(),
+    }
+}
+
+fn subprogram_pos(spec: &SubprogramDeclaration) -> &SrcPos {
+    match spec {
+        SubprogramDeclaration::Function(decl) => decl.designator.pos(),
+        SubprogramDeclaration::Procedure(decl) => decl.designator.pos(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_identifier_respects_word_boundaries() {
+        assert!(contains_identifier("clk <= '1';", "clk"));
+        assert!(!contains_identifier("clkdiv <= '1';", "clk"));
+        assert!(contains_identifier("CLK <= '1';", "clk"));
+    }
+
+    #[test]
+    fn rejects_reserved_words_as_new_names() {
+        assert!(!is_legal_identifier("process"));
+        assert!(!is_legal_identifier("Entity"));
+        assert!(!is_legal_identifier("SIGNAL"));
+    }
+
+    #[test]
+    fn accepts_ordinary_basic_identifiers() {
+        assert!(is_legal_identifier("clk"));
+        assert!(is_legal_identifier("my_signal_1"));
+    }
+
+    #[test]
+    fn rejects_malformed_basic_identifiers() {
+        assert!(!is_legal_identifier("1clk"));
+        assert!(!is_legal_identifier("my__signal"));
+        assert!(!is_legal_identifier("trailing_"));
+    }
+
+    #[test]
+    fn basic_identifiers_compare_case_insensitively() {
+        assert!(identifiers_equal("clk", "CLK"));
+    }
+
+    #[test]
+    fn extended_identifiers_compare_case_sensitively() {
+        assert!(identifiers_equal("\\Foo\\", "\\Foo\\"));
+        assert!(!identifiers_equal("\\Foo\\", "\\foo\\"));
+    }
+
+    #[test]
+    fn extended_identifier_never_equals_basic_identifier() {
+        assert!(!identifiers_equal("\\foo\\", "foo"));
+    }
+
+    #[test]
+    fn substitute_bindings_is_boundary_aware() {
+        let mut bindings = HashMap::new();
+        bindings.insert("x", "A".to_owned());
+        bindings.insert("x1", "B".to_owned());
+        assert_eq!(substitute_bindings("$x1 and $x", &bindings), "B and A");
+    }
+
+    #[test]
+    fn parses_simple_placeholder_name() {
+        assert_eq!(parse_name("$sig"), Some(PatternName::Placeholder("sig".to_owned())));
+    }
+
+    #[test]
+    fn parses_literal_selected_name() {
+        assert_eq!(
+            parse_name("rec.field"),
+            Some(PatternName::Selected(
+                Box::new(PatternName::Simple("rec".to_owned())),
+                "field".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_name_with_trailing_garbage() {
+        assert_eq!(parse_name("sig <="), None);
+    }
+
+    #[test]
+    fn parses_binary_expression_respecting_precedence() {
+        // `$a and $b = $c` should parse as `$a and ($b = $c)` since relational
+        // binds tighter than logical
+        let expr = parse_expr("$a and $b = $c").unwrap();
+        match expr {
+            PatternExpr::Binary(op, left, right) => {
+                assert_eq!(op, "and");
+                assert_eq!(*left, PatternExpr::Placeholder("a".to_owned(), PlaceholderKind::Expression));
+                assert_eq!(
+                    *right,
+                    PatternExpr::Binary(
+                        "=".to_owned(),
+                        Box::new(PatternExpr::Placeholder("b".to_owned(), PlaceholderKind::Expression)),
+                        Box::new(PatternExpr::Placeholder("c".to_owned(), PlaceholderKind::Expression)),
+                    )
+                );
+            }
+            other => panic!("expected Binary, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_unary_minus() {
+        assert_eq!(
+            parse_expr("-$x"),
+            Some(PatternExpr::Unary(
+                "-".to_owned(),
+                Box::new(PatternExpr::Placeholder("x".to_owned(), PlaceholderKind::Expression))
+            ))
+        );
+    }
+
+    #[test]
+    fn statement_pattern_parses_as_statement_placeholder() {
+        match AssignmentPattern::parse("$s;") {
+            Some(AssignmentPattern::Statement(name, PlaceholderKind::Statement)) => {
+                assert_eq!(name, "s")
+            }
+            other => panic!("expected Statement pattern, got {:?}", other.is_some()),
+        }
+    }
+
+    #[test]
+    fn assignment_pattern_parses_target_and_value() {
+        match AssignmentPattern::parse("$tgt <= $val;") {
+            Some(AssignmentPattern::Assignment { target, value }) => {
+                assert_eq!(target, PatternName::Placeholder("tgt".to_owned()));
+                assert_eq!(value, PatternExpr::Placeholder("val".to_owned(), PlaceholderKind::Expression));
+            }
+            other => panic!("expected Assignment pattern, got {:?}", other.is_some()),
+        }
+    }
 }
\ No newline at end of file